@@ -74,133 +74,441 @@
 //! # }
 //! ```
 //!
+//! Structs with more than one field derive `FromUnchecked` from the tuple of
+//! their field types, in declaration order. The tuple's element types are
+//! exactly the struct's field types; no other tuple or struct is accepted:
+//!
+//! ```
+//! # #[macro_use] extern crate uncon_derive;
+//! # extern crate uncon;
+//! # use uncon::*;
+//! #[derive(FromUnchecked)]
+//! struct Point {
+//!     x: u16,
+//!     y: u16,
+//! }
+//!
+//! # fn main() {
+//! unsafe {
+//!     let p = Point::from_unchecked((3u16, 4u16));
+//!     assert_eq!((p.x, p.y), (3, 4));
+//! }
+//! # }
+//! ```
+//!
+//! `#[uncon(impl_from)]` and `#[uncon(try_from)]` derive the safe `From`/
+//! `TryFrom` conversions alongside `FromUnchecked`, for structs and C-like
+//! `#[repr]` enums respectively:
+//!
+//! ```
+//! # #[macro_use] extern crate uncon_derive;
+//! # extern crate uncon;
+//! # use uncon::*;
+//! # use core::convert::TryFrom;
+//! #[derive(FromUnchecked)]
+//! #[uncon(impl_from)]
+//! struct U4 {
+//!     bits: u8
+//! }
+//!
+//! #[derive(FromUnchecked, PartialEq, Debug)]
+//! #[uncon(try_from)]
+//! #[repr(u8)]
+//! enum Flag {
+//!     A, B, C, D
+//! }
+//!
+//! # fn main() {
+//! let x = U4::from(0b1010);
+//! assert_eq!(x.bits, 0b1010);
+//!
+//! assert_eq!(Flag::try_from(2), Ok(Flag::C));
+//! assert!(Flag::try_from(9).is_err());
+//! # }
+//! ```
+//!
+//! # Options
+//!
+//! `#[uncon(..)]` options are parsed with [`darling`], so a misspelled key
+//! (`#[uncon(oher(u16))]`) or a missing `#[repr]` is reported as a normal
+//! compiler error pointing at the offending attribute (for a missing
+//! `#[repr]`, at the enum's name), rather than a panic that aborts the whole
+//! build.
+//!
 //! [crate]: https://crates.io/crates/uncon_derive
 //! [`uncon`]: https://docs.rs/uncon
+//! [`darling`]: https://docs.rs/darling
 //! [`FromUnchecked`]: https://docs.rs/uncon/1.0.0/uncon/trait.FromUnchecked.html
 
-#[macro_use]
-extern crate quote;
-extern crate proc_macro;
-extern crate regex;
-extern crate syn;
-
+use darling::ast::{Data, Fields, Style};
+use darling::{FromDeriveInput, FromField, FromVariant};
 use proc_macro::TokenStream;
-use syn::{Body, MetaItem, NestedMetaItem, VariantData};
-use quote::Tokens;
+use proc_macro2::Span;
+use quote::quote;
+use regex::Regex;
+use syn::{parse_macro_input, DeriveInput, Expr, ExprLit, ExprUnary, Ident, Lit, UnOp};
 
 #[doc(hidden)]
 #[proc_macro_derive(FromUnchecked, attributes(uncon))]
 pub fn from_unchecked(input: TokenStream) -> TokenStream {
-    let ast = syn::parse_derive_input(&input.to_string()).unwrap();
-    impl_from_unchecked(&ast).parse().unwrap()
-}
+    let ast = parse_macro_input!(input as DeriveInput);
 
-fn as_item(item: &NestedMetaItem) -> Option<&MetaItem> {
-    if let NestedMetaItem::MetaItem(ref item) = *item {
-        Some(item)
-    } else {
-        None
+    match UnconArgs::from_derive_input(&ast) {
+        Ok(args) => match impl_from_unchecked(&args) {
+            Ok(tokens) => tokens.into(),
+            Err(err) => err.write_errors().into(),
+        },
+        Err(err) => err.write_errors().into(),
     }
 }
 
-fn meta_items<'a, T: 'a>(items: T, ident: &str) -> Vec<&'a [NestedMetaItem]>
-    where T: IntoIterator<Item=&'a MetaItem>
-{
-    items.into_iter().filter_map(|item| {
-        if let MetaItem::List(ref id, ref items) = *item {
-            if id == ident { return Some(items.as_ref()); }
-        }
-        None
-    }).collect()
+#[derive(Debug, FromField)]
+struct FieldOpts {
+    ident: Option<Ident>,
+    ty: syn::Type,
 }
 
-fn impl_from_unchecked(ast: &syn::DeriveInput) -> quote::Tokens {
-    let name = &ast.ident;
-    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+#[derive(Debug, FromVariant)]
+struct VariantOpts {
+    ident: Ident,
+    discriminant: Option<Expr>,
+    fields: Fields<FieldOpts>,
+}
 
-    let attr_items = |ident: &str| {
-        meta_items(ast.attrs.iter().map(|a| &a.value), ident)
-    };
+#[derive(Debug, FromDeriveInput)]
+#[darling(attributes(uncon), forward_attrs(repr), supports(struct_any, enum_any))]
+struct UnconArgs {
+    ident: Ident,
+    generics: syn::Generics,
+    data: Data<VariantOpts, FieldOpts>,
+    attrs: Vec<syn::Attribute>,
 
-    let core = if cfg!(feature = "std") { quote!(std) } else { quote!(core) };
+    // `multiple` so repeated `#[uncon(other(..))]` attributes accumulate
+    // instead of erroring as a duplicate field; flattened into a single list
+    // by `other_paths` before use.
+    #[darling(multiple, default)]
+    other: Vec<darling::util::PathList>,
+    #[darling(default)]
+    impl_from: bool,
+    #[darling(default)]
+    try_from: bool,
+}
 
-    let (ty, init) = match ast.body {
-        Body::Enum(ref variants) => {
-            for variant in variants {
-                match variant.data {
-                    VariantData::Unit => continue,
-                    _ => panic!("Found non-unit variant '{}'", variant.ident),
+/// Flattens every `#[uncon(other(..))]` attribute's paths into a single
+/// list, since `UnconArgs::other` keeps one `PathList` per occurrence of the
+/// attribute rather than merging them itself.
+fn other_paths(args: &UnconArgs) -> Vec<syn::Path> {
+    args.other.iter().flat_map(|list| list.iter().cloned()).collect()
+}
+
+/// Pulls the integer type out of a forwarded `#[repr(..)]` attribute,
+/// reporting a spanned error (rather than panicking) if one isn't present or
+/// doesn't carry an integer representation.
+fn int_repr(args: &UnconArgs) -> Result<syn::Path, darling::Error> {
+    let int_ty = Regex::new("^(i|u)(\\d+|size)$").unwrap();
+
+    for attr in &args.attrs {
+        if let Ok(syn::Meta::List(list)) = attr.parse_meta() {
+            for nested in &list.nested {
+                if let syn::NestedMeta::Meta(syn::Meta::Path(path)) = nested {
+                    if let Some(ident) = path.get_ident() {
+                        if int_ty.is_match(&ident.to_string()) {
+                            return Ok(path.clone());
+                        }
+                    }
                 }
             }
+        }
+    }
 
-            let items = *attr_items("repr").first().expect("Could not find `#[repr]` attribute");
-            let int_ty = regex::Regex::new("^(i|u)(\\d+|size)$").unwrap();
+    Err(darling::Error::custom(
+        "could not find an integer `#[repr(..)]` for this enum's discriminant"
+    ).with_span(&args.ident))
+}
 
-            let repr = items.iter().filter_map(|ref item| {
-                if let NestedMetaItem::MetaItem(ref item) = **item {
-                    let name = item.name();
-                    if int_ty.is_match(name) {
-                        return Some(name);
-                    }
+/// Parses a variant's explicit discriminant expression, handling both plain
+/// integer literals (`= 2`) and their negation (`= -1`), the latter of which
+/// `syn` represents as a unary-minus expression wrapping the literal rather
+/// than a literal with a leading sign.
+fn discriminant_value(discriminant: &Expr) -> Option<i64> {
+    match discriminant {
+        Expr::Lit(ExprLit { lit: Lit::Int(ref value), .. }) => value.base10_parse().ok(),
+        Expr::Unary(ExprUnary { op: UnOp::Neg(_), ref expr, .. }) => {
+            if let Expr::Lit(ExprLit { lit: Lit::Int(ref value), .. }) = **expr {
+                value.base10_parse::<i64>().ok().map(|v| -v)
+            } else {
+                None
+            }
+        },
+        _ => None,
+    }
+}
+
+/// Returns the integer value of each variant's discriminant, honoring
+/// explicit `= N` values and otherwise continuing the running count from the
+/// last explicit (or implicit) value. An explicit discriminant that is not an
+/// integer literal (or a negated one) is reported as a spanned error.
+fn discriminants(variants: &[VariantOpts]) -> Result<Vec<i64>, darling::Error> {
+    let mut next = 0i64;
+    variants.iter().map(|variant| {
+        let value = match variant.discriminant {
+            Some(ref expr) => discriminant_value(expr).ok_or_else(|| darling::Error::custom(
+                "only integer literal discriminants are supported"
+            ).with_span(expr))?,
+            None => next,
+        };
+        next = value + 1;
+        Ok(value)
+    }).collect()
+}
+
+/// Numbered placeholder idents (`__f0`, `__f1`, …) used to bind the fields of
+/// a tuple variant one at a time before reinterpreting each one.
+fn field_bindings(len: usize) -> Vec<Ident> {
+    (0..len).map(|i| Ident::new(&format!("__f{}", i), Span::call_site())).collect()
+}
+
+/// Builds `match inner { <src>::Variant(..) => <name>::Variant(..), .. }`,
+/// binding every field of every variant from `src` and `transmute`-ing each
+/// binding into the matching field of `name`. This is how a data-carrying
+/// `#[repr]` enum derives `FromUnchecked<Src>` for a `Src` whose variants
+/// line up with its own: the match itself is the discriminant guard, since
+/// there's no arm to fall into for a `Src` value that doesn't share a shape,
+/// and the per-field transmute is what lets `Src`'s field types merely be
+/// layout-compatible with `name`'s rather than identical.
+fn variant_match_arms(variants: &[VariantOpts], name: &Ident, src: &syn::Path) -> proc_macro2::TokenStream {
+    let arms = variants.iter().map(|variant| {
+        let vid = &variant.ident;
+        match variant.fields.style {
+            Style::Unit => quote! {
+                #src::#vid => #name::#vid,
+            },
+            Style::Tuple => {
+                let bindings = field_bindings(variant.fields.fields.len());
+                quote! {
+                    #src::#vid( #(#bindings),* ) =>
+                        #name::#vid( #(::core::mem::transmute(#bindings)),* ),
+                }
+            },
+            Style::Struct => {
+                let idents: Vec<&Ident> = variant.fields.fields.iter()
+                    .map(|f| f.ident.as_ref().expect("struct variant field without a name"))
+                    .collect();
+                quote! {
+                    #src::#vid { #(#idents),* } =>
+                        #name::#vid { #(#idents: ::core::mem::transmute(#idents)),* },
                 }
+            },
+        }
+    });
+
+    quote! {
+        match inner {
+            #(#arms)*
+        }
+    }
+}
+
+fn impl_from_unchecked(args: &UnconArgs) -> Result<proc_macro2::TokenStream, darling::Error> {
+    let name = &args.ident;
+    let (impl_generics, ty_generics, where_clause) = args.generics.split_for_impl();
+
+    // Populated for C-like enums so both the repr impl and any
+    // `#[uncon(other(..))]` widening impls can guard their transmute with a
+    // `debug_assert!` on the discriminant, without affecting release builds.
+    let mut valid_fn = None;
+
+    // Set for enums carrying at least one non-unit variant. Such an enum has
+    // no meaningful integer `Src`, so the only conversions it gets are the
+    // per-variant match/construct impls generated below for each
+    // `#[uncon(other(..))]` entry.
+    let mut data_variants: Option<&[VariantOpts]> = None;
+
+    let primary: Option<(proc_macro2::TokenStream, proc_macro2::TokenStream)> = match args.data {
+        Data::Enum(ref variants) => {
+            let has_data = variants.iter().any(|v| v.fields.style != Style::Unit);
+
+            if has_data {
+                data_variants = Some(variants);
                 None
-            }).next().expect("Could not find integer repr for conversion");
+            } else {
+                let repr = int_repr(args)?;
 
-            let init = quote! { ::#core::mem::transmute(inner) };
-            let mut ty = Tokens::new();
-            ty.append(repr);
+                let discriminants = discriminants(variants)?;
+                let fn_name = Ident::new(&format!("__{}_is_valid_discriminant", name), Span::call_site());
 
-            (ty, init)
+                let init = quote! {
+                    debug_assert!(
+                        #fn_name(inner),
+                        concat!("invalid discriminant for `", stringify!(#name), "`")
+                    );
+                    ::core::mem::transmute(inner)
+                };
+
+                // Unsuffixed so each literal's type is inferred from `inner:
+                // #repr` instead of defaulting to `i64`, which would only
+                // compile for `#[repr(i64)]` enums.
+                let discriminant_lits: Vec<syn::LitInt> = discriminants.iter()
+                    .map(|value| syn::LitInt::new(&value.to_string(), Span::call_site()))
+                    .collect();
+
+                valid_fn = Some(quote! {
+                    #[allow(non_snake_case)]
+                    const fn #fn_name(inner: #repr) -> bool {
+                        match inner {
+                            #(#discriminant_lits)|* => true,
+                            _ => false,
+                        }
+                    }
+                });
+
+                Some((quote!(#repr), init))
+            }
         },
-        Body::Struct(ref data) => {
-            let fields = data.fields();
-            if fields.len() != 1 {
-                panic!("`FromUnchecked` can only be derived for types with a single field");
+        Data::Struct(ref fields) => {
+            if fields.fields.is_empty() {
+                return Err(darling::Error::custom(
+                    "`FromUnchecked` cannot be derived for unit structs"
+                ).with_span(name));
             }
-            let field = &fields[0];
 
-            let init = if let Some(ref ident) = field.ident {
-                quote! { #name { #ident: inner } }
+            if fields.fields.len() == 1 {
+                let field = &fields.fields[0];
+                let init = if let Some(ref ident) = field.ident {
+                    quote! { #name { #ident: inner } }
+                } else {
+                    quote! { #name(inner) }
+                };
+
+                let ty = &field.ty;
+                Some((quote!(#ty), init))
             } else {
-                quote! { #name(inner) }
-            };
+                // `Src` is the tuple of `name`'s own field types; `inner` is
+                // bound by tuple position and each element is transmuted into
+                // the matching field of `name`.
+                let tys = fields.fields.iter().map(|f| &f.ty);
+                let ty = quote! { ( #(#tys),* ) };
+                let indices: Vec<syn::Index> = (0..fields.fields.len()).map(syn::Index::from).collect();
+
+                let init = if fields.fields[0].ident.is_some() {
+                    let idents = fields.fields.iter().map(|f| f.ident.as_ref().unwrap());
+                    quote! { #name { #(#idents: ::core::mem::transmute(inner.#indices)),* } }
+                } else {
+                    quote! { #name( #(::core::mem::transmute(inner.#indices)),* ) }
+                };
 
-            let ty = &field.ty;
-            (quote!(#ty), init)
+                Some((ty, init))
+            }
         },
     };
 
-    let mut other_items = Vec::<&NestedMetaItem>::new();
+    let other = other_paths(args);
 
-    for ai in attr_items("uncon") {
-        for mi in meta_items(ai.iter().filter_map(as_item), "other") {
-            items.extend(mi);
+    if let Some(variants) = data_variants {
+        if other.is_empty() {
+            return Err(darling::Error::custom(
+                "data-carrying `#[repr]` enums have no default `Src`; list at least one \
+                 layout-compatible type via `#[uncon(other(RawTy))]`"
+            ).with_span(name));
         }
+        let _ = variants;
     }
 
-    let tys_impl = other_items.iter().filter_map(|item| {
-        if let NestedMetaItem::MetaItem(MetaItem::Word(ref item)) = **item {
-            Some(quote! {
-                impl #impl_generics ::uncon::FromUnchecked<#item> for #name #ty_generics #where_clause {
+    let tys_impl = other.iter().map(|item| {
+        let body = if let Some(variants) = data_variants {
+            variant_match_arms(variants, name, item)
+        } else {
+            let (ty, _) = primary.as_ref().expect("unit enums and structs always have a primary `Src`");
+            if valid_fn.is_some() {
+                let fn_name = Ident::new(&format!("__{}_is_valid_discriminant", name), Span::call_site());
+                quote! {
+                    let narrowed = inner as #ty;
+                    debug_assert!(
+                        #fn_name(narrowed) && narrowed as #item == inner,
+                        concat!("invalid discriminant for `", stringify!(#name), "`")
+                    );
+                    Self::from_unchecked(narrowed)
+                }
+            } else {
+                quote! { Self::from_unchecked(inner as #ty) }
+            }
+        };
+
+        quote! {
+            impl #impl_generics ::uncon::FromUnchecked<#item> for #name #ty_generics #where_clause {
+                #[inline]
+                unsafe fn from_unchecked(inner: #item) -> Self {
+                    #body
+                }
+            }
+        }
+    });
+
+    let from_impl = if args.impl_from {
+        match primary {
+            Some((ref ty, _)) if valid_fn.is_none() => Some(quote! {
+                impl #impl_generics ::core::convert::From<#ty> for #name #ty_generics #where_clause {
                     #[inline]
-                    unsafe fn from_unchecked(inner: #item) -> Self {
-                        Self::from_unchecked(inner as #ty)
+                    fn from(inner: #ty) -> Self {
+                        unsafe { Self::from_unchecked(inner) }
                     }
                 }
-            })
-        } else {
-            None
+            }),
+            _ => return Err(darling::Error::custom(
+                "`#[uncon(impl_from)]` only applies to structs; use `try_from` for enums"
+            ).with_span(name)),
         }
-    });
+    } else {
+        None
+    };
 
-    quote! {
+    let try_from_impl = if args.try_from {
+        match primary {
+            Some((ref ty, _)) if valid_fn.is_some() => {
+                let fn_name = Ident::new(&format!("__{}_is_valid_discriminant", name), Span::call_site());
+                Some(quote! {
+                    impl #impl_generics ::core::convert::TryFrom<#ty> for #name #ty_generics #where_clause {
+                        type Error = ::uncon::InvalidDiscriminant;
+
+                        #[inline]
+                        fn try_from(inner: #ty) -> ::core::result::Result<Self, Self::Error> {
+                            if #fn_name(inner) {
+                                Ok(unsafe { Self::from_unchecked(inner) })
+                            } else {
+                                Err(::uncon::InvalidDiscriminant)
+                            }
+                        }
+                    }
+                })
+            },
+            _ => return Err(darling::Error::custom(
+                "`#[uncon(try_from)]` only applies to C-like enums; use `impl_from` for structs"
+            ).with_span(name)),
+        }
+    } else {
+        None
+    };
+
+    // `tys_impl` is a lazy iterator that still borrows `primary` (it isn't
+    // consumed until the final `quote!` below), so this has to borrow rather
+    // than move it too.
+    let primary_impl = primary.as_ref().map(|(ty, init)| quote! {
         impl #impl_generics ::uncon::FromUnchecked<#ty> for #name #ty_generics #where_clause {
             #[inline]
             unsafe fn from_unchecked(inner: #ty) -> Self {
                 #init
             }
         }
+    });
+
+    Ok(quote! {
+        #valid_fn
+
+        #primary_impl
 
         #(#tys_impl)*
-    }
+
+        #from_impl
+        #try_from_impl
+    })
 }
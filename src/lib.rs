@@ -54,6 +54,34 @@
 //! assert_eq!(s, "hi");
 //! ```
 //!
+//! The library also ships `FromUnchecked` impls for the standard library's
+//! own invariant-bearing types, wrapping their `unsafe` constructors:
+//!
+//! ```
+//! # use unchecked_convert::*;
+//! # use core::num::NonZeroU8;
+//! unsafe {
+//!     let c: char = 0x41u32.into_unchecked();
+//!     assert_eq!(c, 'A');
+//!
+//!     let n: NonZeroU8 = 5u8.into_unchecked();
+//!     assert_eq!(n.get(), 5);
+//! }
+//! ```
+//!
+//! `Vec<U>` and `Box<[U]>` can be reinterpreted in place as `Vec<T>` and
+//! `Box<[T]>` without reallocating or copying, as long as `U` and `T` share
+//! size and alignment and every element is already a valid `T`:
+//!
+//! ```
+//! # use unchecked_convert::*;
+//! unsafe {
+//!     let v: Vec<u8> = vec![1u8, 2, 3, 4];
+//!     let v: Vec<i8> = v.into_unchecked();
+//!     assert_eq!(v, vec![1i8, 2, 3, 4]);
+//! }
+//! ```
+//!
 //! # Deriving Traits
 //!
 //! Deriving traits requires adding the following to your project's
@@ -124,6 +152,20 @@ use alloc::{String, Vec};
 
 use core::str;
 
+/// Error returned by a derived `TryFrom` conversion when a value does not
+/// match any of the target enum's discriminants.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InvalidDiscriminant;
+
+impl core::fmt::Display for InvalidDiscriminant {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.write_str("value does not match any variant's discriminant")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for InvalidDiscriminant {}
+
 /// Unchecked and potentially unsafe conversions from `T` into `Self`.
 pub trait FromUnchecked<T>: Sized {
     /// Performs the unchecked conversion.
@@ -171,6 +213,43 @@ impl<'a> FromUnchecked<&'a mut [u8]> for &'a mut str {
     }
 }
 
+impl FromUnchecked<u32> for char {
+    #[inline]
+    unsafe fn from_unchecked(code: u32) -> char {
+        char::from_u32_unchecked(code)
+    }
+}
+
+/// Implements `FromUnchecked<$int>` for `$nz` in terms of `$nz::new_unchecked`,
+/// for every non-zero integer type in [`core::num`].
+macro_rules! impl_non_zero {
+    ($($int:ty => $nz:ty),* $(,)*) => {
+        $(
+            impl FromUnchecked<$int> for $nz {
+                #[inline]
+                unsafe fn from_unchecked(n: $int) -> $nz {
+                    <$nz>::new_unchecked(n)
+                }
+            }
+        )*
+    };
+}
+
+impl_non_zero! {
+    u8 => core::num::NonZeroU8,
+    u16 => core::num::NonZeroU16,
+    u32 => core::num::NonZeroU32,
+    u64 => core::num::NonZeroU64,
+    u128 => core::num::NonZeroU128,
+    usize => core::num::NonZeroUsize,
+    i8 => core::num::NonZeroI8,
+    i16 => core::num::NonZeroI16,
+    i32 => core::num::NonZeroI32,
+    i64 => core::num::NonZeroI64,
+    i128 => core::num::NonZeroI128,
+    isize => core::num::NonZeroIsize,
+}
+
 #[cfg(any(feature = "std", feature = "alloc"))]
 impl FromUnchecked<Vec<u8>> for String {
     #[inline]
@@ -186,3 +265,55 @@ impl FromUnchecked<Box<[u8]>> for String {
         utf8.into_vec().into_unchecked()
     }
 }
+
+#[cfg(any(feature = "std", feature = "alloc"))]
+impl FromUnchecked<Box<[u8]>> for Box<str> {
+    #[inline]
+    unsafe fn from_unchecked(utf8: Box<[u8]>) -> Box<str> {
+        Box::from_raw(Box::into_raw(utf8) as *mut str)
+    }
+}
+
+#[cfg(any(feature = "std", feature = "alloc"))]
+impl FromUnchecked<Vec<u8>> for Box<str> {
+    #[inline]
+    unsafe fn from_unchecked(utf8: Vec<u8>) -> Box<str> {
+        utf8.into_boxed_slice().into_unchecked()
+    }
+}
+
+/// Reinterprets a slice of `u16`s as twice as many `u8`s, without validating
+/// or otherwise touching the underlying bytes. Byte order of each `u16` is
+/// preserved, so callers must already know which endianness they want.
+impl<'a> FromUnchecked<&'a [u16]> for &'a [u8] {
+    #[inline]
+    unsafe fn from_unchecked(units: &[u16]) -> &[u8] {
+        core::slice::from_raw_parts(units.as_ptr() as *const u8, units.len() * 2)
+    }
+}
+
+/// Reinterprets every element of a `Vec` in place, without allocating or
+/// copying. The caller must uphold the same contract as the rest of this
+/// crate's pointer-cast impls: `T` and `U` must share size and alignment,
+/// and every `U` in `v` must already be a valid `T`.
+#[cfg(any(feature = "std", feature = "alloc"))]
+impl<T, U> FromUnchecked<Vec<U>> for Vec<T> {
+    #[inline]
+    unsafe fn from_unchecked(v: Vec<U>) -> Vec<T> {
+        let mut v = core::mem::ManuallyDrop::new(v);
+        Vec::from_raw_parts(v.as_mut_ptr() as *mut T, v.len(), v.capacity())
+    }
+}
+
+/// Reinterprets a boxed slice's elements in place, without allocating or
+/// copying. Same size/alignment/validity contract as the `Vec<U> -> Vec<T>`
+/// impl above.
+#[cfg(any(feature = "std", feature = "alloc"))]
+impl<T, U> FromUnchecked<Box<[U]>> for Box<[T]> {
+    #[inline]
+    unsafe fn from_unchecked(b: Box<[U]>) -> Box<[T]> {
+        let len = b.len();
+        let ptr = Box::into_raw(b) as *mut T;
+        Box::from_raw(core::slice::from_raw_parts_mut(ptr, len))
+    }
+}